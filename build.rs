@@ -0,0 +1,99 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+struct Spec {
+    variant: String,
+    pattern: u16,
+    mask: u16,
+    operands: Vec<String>,
+}
+
+fn parse_instructions(src: &str) -> Vec<Spec> {
+    src.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let mut parts = line.split_whitespace();
+            let variant = parts.next().expect("missing variant name").to_string();
+            let pattern = u16::from_str_radix(
+                parts.next().expect("missing pattern").trim_start_matches("0x"),
+                16,
+            )
+            .expect("invalid pattern");
+            let mask = u16::from_str_radix(
+                parts.next().expect("missing mask").trim_start_matches("0x"),
+                16,
+            )
+            .expect("invalid mask");
+            let operands = parts.map(str::to_string).collect();
+
+            Spec { variant, pattern, mask, operands }
+        })
+        .collect()
+}
+
+fn operand_type(token: &str) -> &'static str {
+    match token {
+        "x" | "y" => "Register",
+        "nnn" => "u16",
+        "kk" | "n" => "u8",
+        other => panic!("unknown operand token `{}` in instructions.in", other),
+    }
+}
+
+fn operand_expr(token: &str) -> &'static str {
+    match token {
+        "x" => "Register::V((op >> 8 & 0xF) as usize)",
+        "y" => "Register::V((op >> 4 & 0xF) as usize)",
+        "nnn" => "op & 0x0FFF",
+        "kk" => "op as u8",
+        "n" => "(op & 0x000F) as u8",
+        other => panic!("unknown operand token `{}` in instructions.in", other),
+    }
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=instructions.in");
+
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let src = fs::read_to_string(Path::new(&manifest_dir).join("instructions.in"))
+        .expect("failed to read instructions.in");
+    let specs = parse_instructions(&src);
+
+    let mut out = String::new();
+    out.push_str("#[derive(Debug, Clone, Copy, PartialEq, Eq)]\npub enum Op {\n");
+    for spec in &specs {
+        if spec.operands.is_empty() {
+            out.push_str(&format!("    {},\n", spec.variant));
+        } else {
+            let fields: Vec<&str> = spec.operands.iter().map(|t| operand_type(t)).collect();
+            out.push_str(&format!("    {}({}),\n", spec.variant, fields.join(", ")));
+        }
+    }
+    out.push_str("}\n\n");
+
+    out.push_str("fn decode(op: u16) -> Option<Op> {\n");
+    for spec in &specs {
+        let ctor = if spec.operands.is_empty() {
+            spec.variant.clone()
+        } else {
+            let args: Vec<&str> = spec.operands.iter().map(|t| operand_expr(t)).collect();
+            format!("{}({})", spec.variant, args.join(", "))
+        };
+        if spec.mask == 0xFFFF {
+            // `op & 0xFFFF` is just `op`; matching the full word directly
+            // avoids a no-op mask and the clippy::identity_op it trips.
+            out.push_str(&format!("    if op == {:#06x} {{ return Some(Op::{}); }}\n", spec.pattern, ctor));
+        } else {
+            out.push_str(&format!(
+                "    if op & {:#06x} == {:#06x} {{ return Some(Op::{}); }}\n",
+                spec.mask, spec.pattern, ctor
+            ));
+        }
+    }
+    out.push_str("    None\n}\n");
+
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("instrs.rs"), out).expect("failed to write instrs.rs");
+}
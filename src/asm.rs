@@ -0,0 +1,315 @@
+//! A small two-pass assembler for the mnemonic syntax `Op`'s `Display` impl
+//! emits (see `disassemble` in `lib.rs`), so a ROM can round-trip through
+//! disassembly and back through `assemble`.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::Register;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub col: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.col, self.message)
+    }
+}
+
+fn err(line: usize, col: usize, message: impl Into<String>) -> AsmError {
+    AsmError { line, col, message: message.into() }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Mnemonic(String),
+    Reg(Register),
+    Special(char), // 'K', 'F', 'B', or 'i' for the "[I]" indirect-memory operand
+    Number(u16),
+    Label(String),
+    Comma,
+}
+
+/// Splits one line of source (comment- and whitespace-stripped already is
+/// not assumed; this does that itself) into tokens, reporting the 1-based
+/// column of the first offending character on failure.
+fn lex_line(line: &str, lineno: usize) -> Result<Vec<(Token, usize)>, AsmError> {
+    let code = match line.find(';') {
+        Some(idx) => &line[..idx],
+        None => line,
+    };
+
+    let mut tokens = Vec::new();
+    let mut chars = code.char_indices().peekable();
+
+    while let Some(&(col, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        if c == ',' {
+            chars.next();
+            tokens.push((Token::Comma, col + 1));
+            continue;
+        }
+
+        if c == '[' {
+            let rest = &code[col..];
+            if rest.to_ascii_uppercase().starts_with("[I]") {
+                for _ in 0..3 {
+                    chars.next();
+                }
+                tokens.push((Token::Special('i'), col + 1));
+                continue;
+            }
+            return Err(err(lineno, col + 1, "expected `[I]`"));
+        }
+
+        if c.is_ascii_digit() {
+            let start = col;
+            let mut text = String::new();
+            while let Some(&(_, c)) = chars.peek() {
+                if c.is_ascii_alphanumeric() || c == 'x' || c == 'X' {
+                    text.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            let value = if let Some(hex) = text.strip_prefix("0x").or(text.strip_prefix("0X")) {
+                u16::from_str_radix(hex, 16)
+            } else {
+                text.parse::<u16>()
+            }
+            .map_err(|_| err(lineno, start + 1, format!("invalid number literal `{}`", text)))?;
+            tokens.push((Token::Number(value), start + 1));
+            continue;
+        }
+
+        if c.is_ascii_alphabetic() || c == '_' {
+            let start = col;
+            let mut text = String::new();
+            while let Some(&(_, c)) = chars.peek() {
+                if c.is_ascii_alphanumeric() || c == '_' {
+                    text.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+
+            // A trailing `:` with nothing else on the token marks a label
+            // definition rather than a reference.
+            if chars.peek().map(|&(_, c)| c) == Some(':') {
+                chars.next();
+                tokens.push((Token::Label(text), start + 1));
+                continue;
+            }
+
+            let upper = text.to_ascii_uppercase();
+            if let Some(reg) = parse_register(&upper) {
+                tokens.push((Token::Reg(reg), start + 1));
+            } else if upper == "K" || upper == "F" || upper == "B" {
+                tokens.push((Token::Special(upper.chars().next().unwrap()), start + 1));
+            } else if tokens.iter().all(|(t, _)| matches!(t, Token::Label(_))) {
+                // Only a label definition (if any) precedes the mnemonic.
+                tokens.push((Token::Mnemonic(upper), start + 1));
+            } else {
+                tokens.push((Token::Label(text), start + 1));
+            }
+            continue;
+        }
+
+        return Err(err(lineno, col + 1, format!("unexpected character `{}`", c)));
+    }
+
+    Ok(tokens)
+}
+
+fn parse_register(upper: &str) -> Option<Register> {
+    match upper {
+        "I" => Some(Register::I),
+        "DT" => Some(Register::Dt),
+        "ST" => Some(Register::St),
+        _ => {
+            let digits = upper.strip_prefix('V')?;
+            if digits.len() != 1 {
+                return None;
+            }
+            u8::from_str_radix(digits, 16).ok().map(|n| Register::V(n as usize))
+        }
+    }
+}
+
+/// A line stripped of its label definition (if any) and its remaining
+/// instruction tokens, tagged with the 1-based line number for errors.
+struct Line {
+    number: usize,
+    label: Option<String>,
+    tokens: Vec<(Token, usize)>,
+}
+
+fn split_lines(src: &str) -> Result<Vec<Line>, AsmError> {
+    let mut lines = Vec::new();
+    for (i, raw) in src.lines().enumerate() {
+        let number = i + 1;
+        let mut tokens = lex_line(raw, number)?;
+        if tokens.is_empty() {
+            continue;
+        }
+
+        let label = if let Token::Label(name) = &tokens[0].0 {
+            let name = name.clone();
+            tokens.remove(0);
+            Some(name)
+        } else {
+            None
+        };
+
+        lines.push(Line { number, label, tokens });
+    }
+    Ok(lines)
+}
+
+fn vidx(tok: &Token, line: usize, col: usize) -> Result<usize, AsmError> {
+    match tok {
+        Token::Reg(Register::V(n)) => Ok(*n),
+        _ => Err(err(line, col, "expected a Vx register")),
+    }
+}
+
+fn imm(tok: &Token, bits: u32, line: usize, col: usize) -> Result<u16, AsmError> {
+    match tok {
+        Token::Number(n) if (*n as u32) < (1 << bits) => Ok(*n),
+        Token::Number(n) => Err(err(line, col, format!("immediate {:#x} does not fit in {} bits", n, bits))),
+        _ => Err(err(line, col, "expected an immediate")),
+    }
+}
+
+/// Assembles CHIP-8 source into a ROM image, i.e. the bytes that would be
+/// loaded at `0x200` and handed to `Mmu::load_rom`.
+pub fn assemble(src: &str) -> Result<Vec<u8>, AsmError> {
+    let lines = split_lines(src)?;
+
+    // Pass one: every instruction is exactly one 16-bit word, so label
+    // addresses can be resolved just by counting instruction lines.
+    let mut labels = HashMap::new();
+    let mut addr: u16 = 0x200;
+    for line in &lines {
+        if let Some(name) = &line.label {
+            labels.insert(name.clone(), addr);
+        }
+        if !line.tokens.is_empty() {
+            addr = addr.checked_add(2).ok_or_else(|| err(line.number, 1, "program too large"))?;
+        }
+    }
+
+    // Pass two: resolve each instruction's operands (including label
+    // references) into its 16-bit encoding and emit it big-endian.
+    let mut out = Vec::new();
+    for line in &lines {
+        if line.tokens.is_empty() {
+            continue;
+        }
+        let word = assemble_line(line, &labels)?;
+        out.push((word >> 8) as u8);
+        out.push((word & 0xFF) as u8);
+    }
+    Ok(out)
+}
+
+fn resolve_addr(tok: &Token, labels: &HashMap<String, u16>, line: usize, col: usize) -> Result<u16, AsmError> {
+    match tok {
+        Token::Number(n) if *n <= 0x0FFF => Ok(*n),
+        Token::Number(n) => Err(err(line, col, format!("address {:#x} does not fit in 12 bits", n))),
+        Token::Label(name) => labels.get(name).copied().ok_or_else(|| err(line, col, format!("undefined label `{}`", name))),
+        _ => Err(err(line, col, "expected an address or label")),
+    }
+}
+
+fn assemble_line(line: &Line, labels: &HashMap<String, u16>) -> Result<u16, AsmError> {
+    let n = line.number;
+    let toks: Vec<&Token> = line.tokens.iter().map(|(t, _)| t).collect();
+
+    let mnemonic = match toks.first() {
+        Some(Token::Mnemonic(m)) => m.as_str(),
+        _ => return Err(err(n, 1, "expected a mnemonic")),
+    };
+
+    // Operand tokens with their commas already stripped out.
+    let ops: Vec<&Token> = toks[1..].iter().filter(|t| !matches!(t, Token::Comma)).copied().collect();
+    let op_cols: Vec<usize> =
+        line.tokens[1..].iter().filter(|(t, _)| !matches!(t, Token::Comma)).map(|(_, c)| *c).collect();
+
+    macro_rules! bad_operands {
+        () => {
+            return Err(err(n, 1, format!("`{}` does not take these operands", mnemonic)))
+        };
+    }
+
+    Ok(match (mnemonic, ops.as_slice()) {
+        ("CLS", []) => 0x00E0,
+        ("RET", []) => 0x00EE,
+        ("JP", [addr]) => 0x1000 | resolve_addr(addr, labels, n, op_cols[0])?,
+        ("JP", [Token::Reg(Register::V(0)), addr]) => 0xB000 | resolve_addr(addr, labels, n, op_cols[1])?,
+        ("CALL", [addr]) => 0x2000 | resolve_addr(addr, labels, n, op_cols[0])?,
+        ("SE", [x, Token::Number(_)]) => {
+            0x3000 | ((vidx(x, n, op_cols[0])? as u16) << 8) | imm(ops[1], 8, n, op_cols[1])?
+        }
+        ("SE", [x, y @ Token::Reg(Register::V(_))]) => {
+            0x5000 | ((vidx(x, n, op_cols[0])? as u16) << 8) | ((vidx(y, n, op_cols[1])? as u16) << 4)
+        }
+        ("SNE", [x, Token::Number(_)]) => {
+            0x4000 | ((vidx(x, n, op_cols[0])? as u16) << 8) | imm(ops[1], 8, n, op_cols[1])?
+        }
+        ("SNE", [x, y @ Token::Reg(Register::V(_))]) => {
+            0x9000 | ((vidx(x, n, op_cols[0])? as u16) << 8) | ((vidx(y, n, op_cols[1])? as u16) << 4)
+        }
+        ("LD", [Token::Reg(Register::I), addr]) => 0xA000 | resolve_addr(addr, labels, n, op_cols[1])?,
+        ("LD", [Token::Reg(Register::V(x)), Token::Reg(Register::Dt)]) => 0xF007 | ((*x as u16) << 8),
+        ("LD", [Token::Reg(Register::Dt), x]) => 0xF015 | ((vidx(x, n, op_cols[1])? as u16) << 8),
+        ("LD", [Token::Reg(Register::St), x]) => 0xF018 | ((vidx(x, n, op_cols[1])? as u16) << 8),
+        ("LD", [Token::Reg(Register::V(x)), Token::Special('K')]) => 0xF00A | ((*x as u16) << 8),
+        ("LD", [Token::Special('F'), x]) => 0xF029 | ((vidx(x, n, op_cols[1])? as u16) << 8),
+        ("LD", [Token::Special('B'), x]) => 0xF033 | ((vidx(x, n, op_cols[1])? as u16) << 8),
+        ("LD", [Token::Special('i'), x]) => 0xF055 | ((vidx(x, n, op_cols[1])? as u16) << 8),
+        ("LD", [Token::Reg(Register::V(x)), Token::Special('i')]) => 0xF065 | ((*x as u16) << 8),
+        ("LD", [x, Token::Number(_)]) => {
+            0x6000 | ((vidx(x, n, op_cols[0])? as u16) << 8) | imm(ops[1], 8, n, op_cols[1])?
+        }
+        ("LD", [x, y @ Token::Reg(Register::V(_))]) => {
+            0x8000 | ((vidx(x, n, op_cols[0])? as u16) << 8) | ((vidx(y, n, op_cols[1])? as u16) << 4)
+        }
+        ("ADD", [Token::Reg(Register::I), x]) => 0xF01E | ((vidx(x, n, op_cols[1])? as u16) << 8),
+        ("ADD", [x, Token::Number(_)]) => {
+            0x7000 | ((vidx(x, n, op_cols[0])? as u16) << 8) | imm(ops[1], 8, n, op_cols[1])?
+        }
+        ("ADD", [x, y @ Token::Reg(Register::V(_))]) => {
+            0x8004 | ((vidx(x, n, op_cols[0])? as u16) << 8) | ((vidx(y, n, op_cols[1])? as u16) << 4)
+        }
+        ("OR", [x, y]) => 0x8001 | ((vidx(x, n, op_cols[0])? as u16) << 8) | ((vidx(y, n, op_cols[1])? as u16) << 4),
+        ("AND", [x, y]) => 0x8002 | ((vidx(x, n, op_cols[0])? as u16) << 8) | ((vidx(y, n, op_cols[1])? as u16) << 4),
+        ("XOR", [x, y]) => 0x8003 | ((vidx(x, n, op_cols[0])? as u16) << 8) | ((vidx(y, n, op_cols[1])? as u16) << 4),
+        ("SUB", [x, y]) => 0x8005 | ((vidx(x, n, op_cols[0])? as u16) << 8) | ((vidx(y, n, op_cols[1])? as u16) << 4),
+        ("SUBN", [x, y]) => 0x8007 | ((vidx(x, n, op_cols[0])? as u16) << 8) | ((vidx(y, n, op_cols[1])? as u16) << 4),
+        ("SHR", [x]) => 0x8006 | ((vidx(x, n, op_cols[0])? as u16) << 8),
+        ("SHL", [x]) => 0x800E | ((vidx(x, n, op_cols[0])? as u16) << 8),
+        ("RND", [x, Token::Number(_)]) => {
+            0xC000 | ((vidx(x, n, op_cols[0])? as u16) << 8) | imm(ops[1], 8, n, op_cols[1])?
+        }
+        ("DRAW", [x, y, Token::Number(_)]) => {
+            0xD000
+                | ((vidx(x, n, op_cols[0])? as u16) << 8)
+                | ((vidx(y, n, op_cols[1])? as u16) << 4)
+                | imm(ops[2], 4, n, op_cols[2])?
+        }
+        ("SKP", [x]) => 0xE09E | ((vidx(x, n, op_cols[0])? as u16) << 8),
+        ("SKNP", [x]) => 0xE0A1 | ((vidx(x, n, op_cols[0])? as u16) << 8),
+        (_, _) => bad_operands!(),
+    })
+}
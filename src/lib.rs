@@ -0,0 +1,731 @@
+//! The CHIP-8 interpreter core: `Cpu`, `Mmu`, and the decode/execute path.
+//!
+//! This crate is `no_std` whenever its `std` feature (on by default) is
+//! turned off, so it can be embedded in a bare-metal or WASM frontend; the
+//! bundled binary in `main.rs` (ROM loading, a real RNG seed, the
+//! assembler) requires `std`. The core
+//! never touches a filesystem or a clock itself: a host supplies entropy
+//! via `Cpu::new`'s seed, a key-state source via `Keypad`, and a pixel
+//! sink via `Screen`.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+
+/// A host hook that inspects/repairs `Cpu` state after a `RunError` and
+/// returns whether `step` should resume (`true`) or propagate the error.
+type TrapHandler = Box<dyn FnMut(&mut Cpu, RunError) -> bool>;
+
+// Chip-8's delay/sound timers always count down at 60Hz, independent of how
+// fast the interpreter fetches instructions. This is the default ratio of
+// executed instructions per timer tick for a ~480Hz CPU clock; callers can
+// override it via `Cpu::set_cycles_per_tick` to match a different clock.
+const DEFAULT_CYCLES_PER_TICK: u64 = 8;
+
+/// Everything that can go wrong while stepping the CPU, in place of the
+/// `panic!`s and ad-hoc `String` errors the interpreter used to reach for.
+/// A host can inspect the variant and decide whether to recover via a
+/// `trap_handler` instead of aborting the process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunError {
+    UnknownOpcode(u16),
+    StackOverflow,
+    StackUnderflow,
+    AddressOutOfBounds(usize),
+    InvalidRegister,
+}
+
+impl core::fmt::Display for RunError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            RunError::UnknownOpcode(op) => write!(f, "unknown opcode {:#06x}", op),
+            RunError::StackOverflow => write!(f, "stack overflow"),
+            RunError::StackUnderflow => write!(f, "stack underflow"),
+            RunError::AddressOutOfBounds(addr) => write!(f, "address {:#x} out of bounds", addr),
+            RunError::InvalidRegister => write!(f, "invalid register for this operation"),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum ProgramCounter {
+    Next,
+    Skip,
+    Stay,
+    Jump(usize),
+}
+
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub enum Register {
+    V(usize),
+    I,
+    Dt,
+    St,
+    Pc,
+    Sp,
+}
+
+// Generated by build.rs from instructions.in: the `Op` enum and the
+// table-driven `fn decode(op: u16) -> Option<Op>`.
+include!(concat!(env!("OUT_DIR"), "/instrs.rs"));
+
+#[cfg(feature = "disasm")]
+impl core::fmt::Display for Register {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Register::V(n) => write!(f, "V{:X}", n),
+            Register::I => write!(f, "I"),
+            Register::Dt => write!(f, "DT"),
+            Register::St => write!(f, "ST"),
+            Register::Pc => write!(f, "PC"),
+            Register::Sp => write!(f, "SP"),
+        }
+    }
+}
+
+/// Renders an `Op` as conventional CHIP-8 assembly, e.g. `SE V3, 0x2A` or
+/// `DRAW V0, V1, 5`, the way a disassembler listing or an assembler's
+/// error messages would refer to it.
+#[cfg(feature = "disasm")]
+impl core::fmt::Display for Op {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Op::Cls => write!(f, "CLS"),
+            Op::Ret => write!(f, "RET"),
+            Op::Jp(addr) => write!(f, "JP 0x{:03X}", addr),
+            Op::JpV0Addr(addr) => write!(f, "JP V0, 0x{:03X}", addr),
+            Op::Call(addr) => write!(f, "CALL 0x{:03X}", addr),
+            Op::SeVxByte(x, byte) => write!(f, "SE {}, 0x{:02X}", x, byte),
+            Op::SneVxByte(x, byte) => write!(f, "SNE {}, 0x{:02X}", x, byte),
+            Op::SeVxVy(x, y) => write!(f, "SE {}, {}", x, y),
+            Op::SneVxVy(x, y) => write!(f, "SNE {}, {}", x, y),
+            Op::LdVxByte(x, byte) => write!(f, "LD {}, 0x{:02X}", x, byte),
+            Op::LdVxVy(x, y) => write!(f, "LD {}, {}", x, y),
+            Op::LdIAddr(addr) => write!(f, "LD I, 0x{:03X}", addr),
+            Op::LdVxDt(x) => write!(f, "LD {}, DT", x),
+            Op::LdDtVx(x) => write!(f, "LD DT, {}", x),
+            Op::LdStVx(x) => write!(f, "LD ST, {}", x),
+            Op::AddVxByte(x, byte) => write!(f, "ADD {}, 0x{:02X}", x, byte),
+            Op::AddVxVy(x, y) => write!(f, "ADD {}, {}", x, y),
+            Op::AddIVx(x) => write!(f, "ADD I, {}", x),
+            Op::OrVxVy(x, y) => write!(f, "OR {}, {}", x, y),
+            Op::AndVxVy(x, y) => write!(f, "AND {}, {}", x, y),
+            Op::XorVxVy(x, y) => write!(f, "XOR {}, {}", x, y),
+            Op::SubVxVy(x, y) => write!(f, "SUB {}, {}", x, y),
+            Op::SubnVxVy(x, y) => write!(f, "SUBN {}, {}", x, y),
+            Op::ShrVx(x) => write!(f, "SHR {}", x),
+            Op::ShlVx(x) => write!(f, "SHL {}", x),
+            Op::RndVxByte(x, byte) => write!(f, "RND {}, 0x{:02X}", x, byte),
+            Op::Draw(x, y, n) => write!(f, "DRAW {}, {}, {}", x, y, n),
+            Op::SkipKeyVx(x) => write!(f, "SKP {}", x),
+            Op::SkipNoKeyVx(x) => write!(f, "SKNP {}", x),
+            Op::WaitKeyVx(x) => write!(f, "LD {}, K", x),
+            Op::SpriteCharVx(x) => write!(f, "LD F, {}", x),
+            Op::MovBcdVx(x) => write!(f, "LD B, {}", x),
+            Op::ReadMemVx(x) => write!(f, "LD [I], {}", x),
+            Op::WriteMemVx(x) => write!(f, "LD {}, [I]", x),
+        }
+    }
+}
+
+/// Walks a ROM image and decodes it two bytes at a time starting at the
+/// conventional CHIP-8 load address (`0x200`), without constructing a
+/// `Cpu`. Stops at the first byte pair that doesn't decode to a known
+/// `Op`, since from there on a straight-line disassembly can no longer
+/// tell code from data.
+#[cfg(feature = "disasm")]
+pub fn disassemble(rom: &[u8]) -> impl Iterator<Item = (usize, Op)> + '_ {
+    let mut addr = 0x200;
+    core::iter::from_fn(move || {
+        let hi = *rom.get(addr - 0x200)?;
+        let lo = *rom.get(addr - 0x200 + 1)?;
+        let op = decode(((hi as u16) << 8) | lo as u16)?;
+        let here = addr;
+        addr += 2;
+        Some((here, op))
+    })
+}
+
+/// The 64x32 monochrome pixel sink the core calls into on `CLS`/`DRAW`, so
+/// embedders can render however they like (terminal, framebuffer, GPU
+/// texture) without the core depending on any particular backend.
+pub trait Screen {
+    fn clear(&mut self);
+
+    /// XORs a set pixel into `(x, y)` and returns whether a previously-set
+    /// pixel was erased, i.e. a collision.
+    fn xor_pixel(&mut self, x: usize, y: usize) -> bool;
+}
+
+/// The 16-key CHIP-8 keypad (`0`-`F`), polled by `SKP`/`SKNP`/`LD Vx, K`.
+pub trait Keypad {
+    fn is_down(&self, key: u8) -> bool;
+
+    /// Returns the lowest-numbered key currently held down, if any.
+    fn first_down(&self) -> Option<u8> {
+        (0u8..16).find(|&key| self.is_down(key))
+    }
+}
+
+// The standard CHIP-8 hex digit sprites (0-F), 5 bytes each, conventionally
+// loaded at 0x000 so `SPRITECHAR` can point `I` at `digit * 5`.
+const FONTSET: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+pub struct Mmu {
+    ram: [u8; 4096],
+}
+
+impl Mmu {
+    pub fn new() -> Mmu {
+        let mut ram = [0; 4096];
+        ram[..FONTSET.len()].copy_from_slice(&FONTSET);
+        Mmu { ram }
+    }
+
+    fn read_byte(&self, index: usize) -> Result<u8, RunError> {
+        self.ram.get(index).copied().ok_or(RunError::AddressOutOfBounds(index))
+    }
+
+    fn write_byte(&mut self, index: usize, value: u8) -> Result<(), RunError> {
+        *self.ram.get_mut(index).ok_or(RunError::AddressOutOfBounds(index))? = value;
+        Ok(())
+    }
+
+    fn read_word(&self, index: usize) -> Result<u16, RunError> {
+        let hi = self.read_byte(index)?;
+        let lo = self.read_byte(index + 1)?;
+        Ok(((hi as u16) << 8) | lo as u16)
+    }
+
+    /// Loads `rom` at the conventional CHIP-8 program start address, `0x200`.
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), RunError> {
+        let end = 0x200usize
+            .checked_add(rom.len())
+            .ok_or(RunError::AddressOutOfBounds(usize::MAX))?;
+        self.ram
+            .get_mut(0x200..end)
+            .ok_or(RunError::AddressOutOfBounds(end))?
+            .copy_from_slice(rom);
+        Ok(())
+    }
+}
+
+impl Default for Mmu {
+    fn default() -> Mmu {
+        Mmu::new()
+    }
+}
+
+pub struct Cpu {
+    mmu: Mmu,
+    // general purpose registers
+    v: [u8; 16],
+
+    // address store register
+    i: usize,
+
+    stack: [usize; 16],
+    pc: usize,
+    sp: usize,
+
+    // delay and sound timers
+    dt: u8,
+    st: u8,
+
+    // counts executed instructions so timers can be ticked every
+    // `cycles_per_tick` of them, approximating a steady 60Hz independent of
+    // the CPU's own clock speed
+    instr_count: u64,
+    cycles_per_tick: u64,
+
+    // xorshift state for RND
+    rng_state: u64,
+
+    // lets a host inspect/repair CPU state on a `RunError` and decide
+    // whether `step` should resume or propagate the error
+    trap_handler: Option<TrapHandler>,
+}
+
+impl Cpu {
+    /// `seed` drives the xorshift64 RNG behind `RND`. The core has no
+    /// clock of its own to draw entropy from, so the host provides one
+    /// (e.g. a wall-clock reading on `std`, or a hardware RNG on
+    /// bare metal).
+    pub fn new(mmu: Mmu, seed: u64) -> Cpu {
+        Cpu {
+            mmu,
+            v: [0u8; 16],
+            i: 0,
+            stack: [0usize; 16],
+            pc: 0x200,
+            sp: 0,
+            dt: 0,
+            st: 0,
+            instr_count: 0,
+            cycles_per_tick: DEFAULT_CYCLES_PER_TICK,
+            rng_state: seed | 1,
+            trap_handler: None,
+        }
+    }
+
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn set_cycles_per_tick(&mut self, cycles_per_tick: u64) {
+        self.cycles_per_tick = cycles_per_tick;
+    }
+
+    pub fn set_trap_handler(&mut self, handler: impl FnMut(&mut Cpu, RunError) -> bool + 'static) {
+        self.trap_handler = Some(Box::new(handler));
+    }
+
+    fn tick_timers(&mut self) {
+        if self.dt > 0 {
+            self.dt -= 1;
+        }
+        if self.st > 0 {
+            self.st -= 1;
+        }
+    }
+
+    pub fn is_beeping(&self) -> bool {
+        self.st > 0
+    }
+
+    fn next_rand_byte(&mut self) -> u8 {
+        // xorshift64
+        let mut x = self.rng_state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.rng_state = x;
+        (x >> 24) as u8
+    }
+
+    fn read_instruction(&mut self) -> Result<Op, RunError> {
+        let op = self.mmu.read_word(self.pc)?;
+        decode(op).ok_or(RunError::UnknownOpcode(op))
+    }
+
+    fn execute_instruction(
+        &mut self,
+        instruction: Op,
+        screen: &mut impl Screen,
+        keypad: &impl Keypad,
+    ) -> Result<(), RunError> {
+        let pc_change = match instruction {
+            Op::Cls => {
+                screen.clear();
+                ProgramCounter::Next
+            }
+            Op::Ret => {
+                self.sp = self.sp.checked_sub(1).ok_or(RunError::StackUnderflow)?;
+                ProgramCounter::Jump(self.stack[self.sp])
+            }
+            Op::Jp(dst) => ProgramCounter::Jump(dst as usize),
+            Op::JpV0Addr(dst) => ProgramCounter::Jump(self.v[0] as usize + dst as usize),
+            Op::Call(dst) => {
+                if self.sp >= self.stack.len() {
+                    return Err(RunError::StackOverflow);
+                }
+                self.stack[self.sp] = self.pc + 2;
+                self.sp += 1;
+                ProgramCounter::Jump(dst as usize)
+            }
+            Op::SeVxByte(x, byte) => {
+                if self.v[Self::vidx(x)] == byte {
+                    ProgramCounter::Skip
+                } else {
+                    ProgramCounter::Next
+                }
+            }
+            Op::SneVxByte(x, byte) => {
+                if self.v[Self::vidx(x)] != byte {
+                    ProgramCounter::Skip
+                } else {
+                    ProgramCounter::Next
+                }
+            }
+            Op::SeVxVy(x, y) => {
+                if self.v[Self::vidx(x)] == self.v[Self::vidx(y)] {
+                    ProgramCounter::Skip
+                } else {
+                    ProgramCounter::Next
+                }
+            }
+            Op::SneVxVy(x, y) => {
+                if self.v[Self::vidx(x)] != self.v[Self::vidx(y)] {
+                    ProgramCounter::Skip
+                } else {
+                    ProgramCounter::Next
+                }
+            }
+            Op::LdVxByte(x, byte) => {
+                self.v[Self::vidx(x)] = byte;
+                ProgramCounter::Next
+            }
+            Op::LdVxVy(x, y) => {
+                self.v[Self::vidx(x)] = self.v[Self::vidx(y)];
+                ProgramCounter::Next
+            }
+            Op::LdIAddr(addr) => {
+                self.i = addr as usize;
+                ProgramCounter::Next
+            }
+            Op::LdVxDt(x) => {
+                self.v[Self::vidx(x)] = self.dt;
+                ProgramCounter::Next
+            }
+            Op::LdDtVx(x) => {
+                self.dt = self.v[Self::vidx(x)];
+                ProgramCounter::Next
+            }
+            Op::LdStVx(x) => {
+                self.st = self.v[Self::vidx(x)];
+                ProgramCounter::Next
+            }
+            Op::AddVxByte(x, byte) => {
+                let x = Self::vidx(x);
+                self.v[x] = self.v[x].wrapping_add(byte);
+                ProgramCounter::Next
+            }
+            Op::AddVxVy(x, y) => {
+                let x = Self::vidx(x);
+                let (result, carry) = self.v[x].overflowing_add(self.v[Self::vidx(y)]);
+                self.v[x] = result;
+                self.v[0xF] = carry as u8;
+                ProgramCounter::Next
+            }
+            Op::AddIVx(x) => {
+                self.i += self.v[Self::vidx(x)] as usize;
+                ProgramCounter::Next
+            }
+            Op::OrVxVy(x, y) => self.alu(x, y, |a, b| a | b),
+            Op::AndVxVy(x, y) => self.alu(x, y, |a, b| a & b),
+            Op::XorVxVy(x, y) => self.alu(x, y, |a, b| a ^ b),
+            Op::SubVxVy(x, y) => {
+                let x = Self::vidx(x);
+                let (result, borrow) = self.v[x].overflowing_sub(self.v[Self::vidx(y)]);
+                self.v[x] = result;
+                self.v[0xF] = !borrow as u8;
+                ProgramCounter::Next
+            }
+            Op::SubnVxVy(x, y) => {
+                let x = Self::vidx(x);
+                let (result, borrow) = self.v[Self::vidx(y)].overflowing_sub(self.v[x]);
+                self.v[x] = result;
+                self.v[0xF] = !borrow as u8;
+                ProgramCounter::Next
+            }
+            Op::ShrVx(x) => {
+                let x = Self::vidx(x);
+                self.v[0xF] = self.v[x] & 0x1;
+                self.v[x] >>= 1;
+                ProgramCounter::Next
+            }
+            Op::ShlVx(x) => {
+                let x = Self::vidx(x);
+                self.v[0xF] = (self.v[x] >> 7) & 0x1;
+                self.v[x] <<= 1;
+                ProgramCounter::Next
+            }
+            Op::RndVxByte(x, mask) => {
+                let byte = self.next_rand_byte();
+                self.v[Self::vidx(x)] = byte & mask;
+                ProgramCounter::Next
+            }
+            Op::Draw(x, y, n) => {
+                let x = self.v[Self::vidx(x)] as usize;
+                let y = self.v[Self::vidx(y)] as usize;
+                self.draw_sprite(x, y, n as usize, screen)?;
+                ProgramCounter::Next
+            }
+            Op::SkipKeyVx(x) => {
+                if keypad.is_down(self.v[Self::vidx(x)]) {
+                    ProgramCounter::Skip
+                } else {
+                    ProgramCounter::Next
+                }
+            }
+            Op::SkipNoKeyVx(x) => {
+                if keypad.is_down(self.v[Self::vidx(x)]) {
+                    ProgramCounter::Next
+                } else {
+                    ProgramCounter::Skip
+                }
+            }
+            Op::WaitKeyVx(x) => match keypad.first_down() {
+                Some(key) => {
+                    self.v[Self::vidx(x)] = key;
+                    ProgramCounter::Next
+                }
+                // No key down yet: re-execute this same instruction next
+                // `step`, which is how real CHIP-8 hardware "blocks".
+                None => ProgramCounter::Stay,
+            },
+            Op::SpriteCharVx(x) => {
+                self.i = self.v[Self::vidx(x)] as usize * 5;
+                ProgramCounter::Next
+            }
+            Op::MovBcdVx(x) => {
+                let value = self.v[Self::vidx(x)];
+                let i = self.i;
+                self.mmu.write_byte(i, value / 100)?;
+                self.mmu.write_byte(i + 1, (value / 10) % 10)?;
+                self.mmu.write_byte(i + 2, value % 10)?;
+                ProgramCounter::Next
+            }
+            Op::ReadMemVx(x) => {
+                let x = Self::vidx(x);
+                for offset in 0..=x {
+                    self.mmu.write_byte(self.i + offset, self.v[offset])?;
+                }
+                ProgramCounter::Next
+            }
+            Op::WriteMemVx(x) => {
+                let x = Self::vidx(x);
+                for offset in 0..=x {
+                    self.v[offset] = self.mmu.read_byte(self.i + offset)?;
+                }
+                ProgramCounter::Next
+            }
+        };
+
+        match pc_change {
+            ProgramCounter::Next => self.pc += 2,
+            ProgramCounter::Skip => self.pc += 4,
+            ProgramCounter::Stay => {}
+            ProgramCounter::Jump(addr) => self.pc = addr,
+        }
+
+        self.instr_count += 1;
+        if self.instr_count.is_multiple_of(self.cycles_per_tick) {
+            self.tick_timers();
+        }
+
+        Ok(())
+    }
+
+    /// Fetches, decodes, and executes one instruction. Unlike the bare
+    /// `execute_instruction`, this also gives an installed `trap_handler`
+    /// the chance to inspect/repair CPU state and decide whether execution
+    /// should resume or halt.
+    pub fn step(&mut self, screen: &mut impl Screen, keypad: &impl Keypad) -> Result<(), RunError> {
+        let result = self.read_instruction().and_then(|op| self.execute_instruction(op, screen, keypad));
+
+        match result {
+            Ok(()) => Ok(()),
+            Err(err) => match self.trap_handler.take() {
+                Some(mut handler) => {
+                    let resume = handler(self, err);
+                    self.trap_handler = Some(handler);
+                    if resume {
+                        Ok(())
+                    } else {
+                        Err(err)
+                    }
+                }
+                None => Err(err),
+            },
+        }
+    }
+
+    /// Extracts the `v` index out of a `Register::V`. Decode only ever
+    /// produces `Register::V` for operand positions typed `Register`, so
+    /// this can't observe `I`/`Dt`/`St`/`Pc`/`Sp`.
+    fn vidx(register: Register) -> usize {
+        match register {
+            Register::V(n) => n,
+            _ => unreachable!("{:?} is not a Vx operand", register),
+        }
+    }
+
+    fn alu(&mut self, x: Register, y: Register, f: impl Fn(u8, u8) -> u8) -> ProgramCounter {
+        let x = Self::vidx(x);
+        let y = self.v[Self::vidx(y)];
+        self.v[x] = f(self.v[x], y);
+        ProgramCounter::Next
+    }
+
+    fn draw_sprite(
+        &mut self,
+        x: usize,
+        y: usize,
+        n: usize,
+        screen: &mut impl Screen,
+    ) -> Result<(), RunError> {
+        self.v[0xF] = 0;
+        for row in 0..n {
+            let sprite_byte = self.mmu.read_byte(self.i + row)?;
+            for col in 0..8 {
+                if sprite_byte & (0x80 >> col) == 0 {
+                    continue;
+                }
+
+                let px = (x + col) % 64;
+                let py = (y + row) % 32;
+                if screen.xor_pixel(px, py) {
+                    self.v[0xF] = 1;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestScreen {
+        pixels: [bool; 64 * 32],
+    }
+
+    impl TestScreen {
+        fn new() -> TestScreen {
+            TestScreen { pixels: [false; 64 * 32] }
+        }
+    }
+
+    impl Screen for TestScreen {
+        fn clear(&mut self) {
+            self.pixels = [false; 64 * 32];
+        }
+
+        fn xor_pixel(&mut self, x: usize, y: usize) -> bool {
+            let idx = y * 64 + x;
+            let was_set = self.pixels[idx];
+            self.pixels[idx] = !was_set;
+            was_set
+        }
+    }
+
+    struct NoKeys;
+
+    impl Keypad for NoKeys {
+        fn is_down(&self, _key: u8) -> bool {
+            false
+        }
+    }
+
+    fn cpu_with(rom: &[u8]) -> Cpu {
+        let mut mmu = Mmu::new();
+        mmu.load_rom(rom).unwrap();
+        Cpu::new(mmu, 1)
+    }
+
+    fn step(cpu: &mut Cpu) {
+        cpu.step(&mut TestScreen::new(), &NoKeys).unwrap();
+    }
+
+    #[test]
+    fn add_vx_byte_then_add_vx_vy() {
+        // LD V0, 0x05; LD V1, 0x01; ADD V0, V1
+        let mut cpu = cpu_with(&[0x60, 0x05, 0x61, 0x01, 0x80, 0x14]);
+        step(&mut cpu);
+        step(&mut cpu);
+        step(&mut cpu);
+        assert_eq!(cpu.v[0], 6);
+        assert_eq!(cpu.v[0xF], 0);
+    }
+
+    #[test]
+    fn add_vx_vy_sets_vf_on_overflow() {
+        // LD V0, 0xFF; LD V1, 0x01; ADD V0, V1
+        let mut cpu = cpu_with(&[0x60, 0xFF, 0x61, 0x01, 0x80, 0x14]);
+        step(&mut cpu);
+        step(&mut cpu);
+        step(&mut cpu);
+        assert_eq!(cpu.v[0], 0);
+        assert_eq!(cpu.v[0xF], 1);
+    }
+
+    #[test]
+    fn shr_shifts_out_the_low_bit_into_vf() {
+        // LD V0, 0x03; SHR V0
+        let mut cpu = cpu_with(&[0x60, 0x03, 0x80, 0x06]);
+        step(&mut cpu);
+        step(&mut cpu);
+        assert_eq!(cpu.v[0], 0x01);
+        assert_eq!(cpu.v[0xF], 1);
+    }
+
+    #[test]
+    fn shl_shifts_out_the_high_bit_into_vf() {
+        // LD V0, 0x81; SHL V0
+        let mut cpu = cpu_with(&[0x60, 0x81, 0x80, 0x0E]);
+        step(&mut cpu);
+        step(&mut cpu);
+        assert_eq!(cpu.v[0], 0x02);
+        assert_eq!(cpu.v[0xF], 1);
+    }
+
+    #[test]
+    fn read_mem_writes_v0_through_vx_to_ram_at_i() {
+        // LD V0, 0xAA; LD V1, 0xBB; LD I, 0x300; LD [I], V1
+        let mut cpu = cpu_with(&[0x60, 0xAA, 0x61, 0xBB, 0xA3, 0x00, 0xF1, 0x55]);
+        for _ in 0..4 {
+            step(&mut cpu);
+        }
+        assert_eq!(cpu.mmu.read_byte(0x300).unwrap(), 0xAA);
+        assert_eq!(cpu.mmu.read_byte(0x301).unwrap(), 0xBB);
+    }
+
+    #[test]
+    fn write_mem_loads_v0_through_vx_from_ram_at_i() {
+        // Poke 0x11, 0x22 into RAM at 0x300 directly, then run
+        // LD I, 0x300; LD V1, [I]
+        let mut cpu = cpu_with(&[0xA3, 0x00, 0xF1, 0x65]);
+        cpu.mmu.write_byte(0x300, 0x11).unwrap();
+        cpu.mmu.write_byte(0x301, 0x22).unwrap();
+        step(&mut cpu);
+        step(&mut cpu);
+        assert_eq!(cpu.v[0], 0x11);
+        assert_eq!(cpu.v[1], 0x22);
+    }
+
+    #[test]
+    fn sprite_char_points_i_at_the_builtin_font() {
+        // LD V0, 0x0; LD F, V0
+        let mut cpu = cpu_with(&[0x60, 0x00, 0xF0, 0x29]);
+        step(&mut cpu);
+        step(&mut cpu);
+        assert_eq!(cpu.i, 0);
+        assert_eq!(cpu.mmu.read_byte(0).unwrap(), 0xF0);
+    }
+
+    #[test]
+    fn decode_rejects_unknown_opcodes() {
+        // 0x0000 and 0x5001 (a bad SE encoding) aren't in instructions.in.
+        assert!(decode(0x0000).is_none());
+        assert!(decode(0x5001).is_none());
+    }
+
+    #[cfg(feature = "disasm")]
+    #[test]
+    fn display_renders_canonical_mnemonics() {
+        use alloc::format;
+        assert_eq!(format!("{}", decode(0x00E0).unwrap()), "CLS");
+        assert_eq!(format!("{}", decode(0x3A2A).unwrap()), "SE VA, 0x2A");
+        assert_eq!(format!("{}", decode(0xD125).unwrap()), "DRAW V1, V2, 5");
+    }
+}